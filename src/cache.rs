@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a previous `process_crate` run for a given `(crate, version)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildStatus {
+    Success,
+    Failure,
+}
+
+/// A single `(crate, version)` entry in the [`BuildCache`] manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub status: BuildStatus,
+    pub output_path: String,
+    pub work_dir: String,
+    pub source: String,
+    pub last_touched_secs: u64,
+}
+
+/// A manifest of per-`(crate, version)` build outcomes, persisted as JSON under `BASE_PATH`.
+///
+/// `process_crate` consults this before doing any git fetch or rustdoc build, so that crates
+/// whose `(name, version)` already succeeded and whose output file still exists can be skipped
+/// entirely. This is what turns the tool from a full rebuild-every-run script into an incremental
+/// generator.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+fn cache_key(crate_name: &str, version: &str) -> String {
+    format!("{crate_name}@{version}")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Recursively sum the size in bytes of all files under `path`.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                dir_size(&entry_path)
+            } else {
+                fs::metadata(&entry_path).map(|m| m.len()).unwrap_or(0)
+            }
+        })
+        .sum()
+}
+
+impl BuildCache {
+    /// Load the manifest from `manifest_path`, or start with an empty one if it doesn't exist yet.
+    pub fn load(manifest_path: &str) -> anyhow::Result<Self> {
+        if !Path::new(manifest_path).is_file() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(manifest_path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, manifest_path: &str) -> anyhow::Result<()> {
+        if let Some(parent) = Path::new(manifest_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(manifest_path, contents)?;
+        Ok(())
+    }
+
+    /// Whether `(crate_name, version)` already has a successful entry pointing at a file that
+    /// still exists on disk, meaning the git fetch / rustdoc build can be skipped outright.
+    pub fn has_fresh_success(&self, crate_name: &str, version: &str) -> bool {
+        self.entries.get(&cache_key(crate_name, version)).is_some_and(|entry| {
+            entry.status == BuildStatus::Success && Path::new(&entry.output_path).is_file()
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        crate_name: &str,
+        version: &str,
+        status: BuildStatus,
+        output_path: &str,
+        work_dir: &str,
+        source: &str,
+    ) {
+        self.entries.insert(
+            cache_key(crate_name, version),
+            CacheEntry {
+                status,
+                output_path: output_path.to_owned(),
+                work_dir: work_dir.to_owned(),
+                source: source.to_owned(),
+                last_touched_secs: now_secs(),
+            },
+        );
+    }
+
+    /// Delete cloned repos / extracted tarballs and generated JSON for entries not touched within
+    /// `max_age_secs`, removing them from the manifest. Returns the number of bytes reclaimed.
+    pub fn prune(&mut self, max_age_secs: u64) -> anyhow::Result<u64> {
+        let now = now_secs();
+        let mut reclaimed_bytes = 0u64;
+        let mut stale_keys = vec![];
+
+        for (key, entry) in self.entries.iter() {
+            if now.saturating_sub(entry.last_touched_secs) < max_age_secs {
+                continue;
+            }
+
+            let work_dir_path = Path::new(&entry.work_dir);
+            if work_dir_path.is_dir() {
+                reclaimed_bytes += dir_size(work_dir_path);
+                fs::remove_dir_all(work_dir_path)?;
+            }
+
+            let output_path = Path::new(&entry.output_path);
+            if let Ok(metadata) = fs::metadata(output_path) {
+                reclaimed_bytes += metadata.len();
+                fs::remove_file(output_path)?;
+            }
+
+            stale_keys.push(key.clone());
+        }
+
+        for key in stale_keys {
+            self.entries.remove(&key);
+        }
+
+        Ok(reclaimed_bytes)
+    }
+}
@@ -1,16 +1,26 @@
 use std::{
+    collections::VecDeque,
     fs::File,
     io::{BufWriter, Write},
     path::Path,
+    sync::Mutex,
     time::Duration,
 };
 
-use crates_io_api::{Crate, CratesQuery, Sort};
+use cache::{BuildCache, BuildStatus};
+use crates_io_api::{Crate, CratesQuery, Sort, SyncClient};
 use duct::cmd;
+use flate2::read::GzDecoder;
+use tar::Archive;
+
+mod cache;
+mod semver_check;
 
 static BASE_PATH: &str = "/var/tmp/crates-rustdoc-gen/";
 static GITHUB_PREFIX: &str = "https://github.com/";
 static GITHUB_GIT_PREFIX: &str = "git@github.com:";
+static CRATES_IO_STATIC_PREFIX: &str = "https://static.crates.io/crates/";
+static CACHE_MANIFEST_PATH: &str = "/var/tmp/crates-rustdoc-gen/cache.json";
 
 fn check_if_git_tag_exists(repo_path: &str, tag_name: &str) -> anyhow::Result<bool> {
     let result = cmd!("git", "tag", "-l", tag_name)
@@ -40,81 +50,85 @@ fn discover_tag_format(repo_path: &str, crate_name: &str, version: &str) -> Opti
     None
 }
 
-fn process_github_repo(
-    crate_info: &Crate,
-    repo_path: &str,
-    check_semver: bool,
-) -> anyhow::Result<()> {
-    println!("* crate {} *", &crate_info.name);
-
-    let org_and_repo_name = repo_path
-        .strip_prefix(GITHUB_PREFIX)
-        .expect("invalid prefix")
-        .trim_end_matches('/')
-        .trim_end_matches(".git");
-    let git_path = format!("{}{}.git", GITHUB_GIT_PREFIX, org_and_repo_name);
+/// All non-yanked, non-pre-release published versions of `crate_name`, greatest first.
+fn stable_versions(client: &SyncClient, crate_name: &str) -> anyhow::Result<Vec<(semver::Version, String)>> {
+    let response = client.get_crate(crate_name)?;
 
-    let repo_name = org_and_repo_name.replace('/', "__");
-    let local_repo_path = format!("{}{}", BASE_PATH, repo_name);
-
-    // Have we already downloaded this repo?
-    if Path::new(&format!("{}/.git", &local_repo_path)).is_dir() {
-        // Yes! Run "git pull" to make sure it's up to date.
-        println!(">> refreshing repo {local_repo_path}");
-        cmd!("git", "fetch").dir(&local_repo_path).run()?;
-    } else {
-        // No. Clone it into that directory.
-        println!(">> cloning into {local_repo_path}");
-        cmd!("git", "clone", git_path, &local_repo_path).run()?;
-    }
+    let mut versions: Vec<(semver::Version, String)> = response
+        .versions
+        .into_iter()
+        .filter(|version| !version.yanked)
+        .filter_map(|version| semver::Version::parse(&version.num).ok().map(|parsed| (parsed, version.num)))
+        .filter(|(parsed, _)| parsed.pre.is_empty())
+        .collect();
+    versions.sort_by(|(a, _), (b, _)| b.cmp(a));
 
-    // Check out the tag corresponding to the biggest version number.
-    // We would have loved to be able to get the "max_stable_version" instead,
-    // but that's not something the crates_io_api crate supports.
-    let max_version = crate_info.max_version.as_str();
-    println!(">> crate {} max version is {max_version}", &crate_info.name);
+    Ok(versions)
+}
 
-    let tag_name = match discover_tag_format(&local_repo_path, &crate_info.name, max_version) {
-        Some(tag_name) => tag_name,
-        None => {
-            println!(
-                ">> could not find version tag for crate version {} {} in repo {}",
-                crate_info.name, max_version, local_repo_path
-            );
-            return Ok(());
-        }
-    };
+/// The newest version in `versions` (as returned by `stable_versions`, greatest first), or
+/// `fallback_version` if `versions` is empty (i.e. every published version is a pre-release).
+///
+/// `crate_info.max_version` can point at an alpha/beta/rc release, since crates_io_api doesn't
+/// expose crates.io's own notion of "max stable version"; passing it as `fallback_version` works
+/// around that.
+fn max_stable_version(versions: &[(semver::Version, String)], fallback_version: &str) -> String {
+    versions
+        .first()
+        .map(|(_, num)| num.clone())
+        .unwrap_or_else(|| fallback_version.to_owned())
+}
 
-    if check_semver {
-        if let Err(e) = attempt_cargo_semver_checks(&local_repo_path, &crate_info.name) {
-            println!(
-                ">> cargo-semver-checks run failed for crate version
-                {} {max_version} in repo {local_repo_path}: {e}",
-                crate_info.name,
-            )
-        }
-    }
+/// The version in `versions` immediately preceding `current_version`, for use as the
+/// `cargo-semver-checks` baseline. Returns `None` if `current_version` is the oldest stable
+/// release, and an error if `current_version` isn't itself valid semver.
+fn preceding_stable_version(
+    versions: &[(semver::Version, String)],
+    current_version: &str,
+) -> anyhow::Result<Option<String>> {
+    let current = semver::Version::parse(current_version)?;
+    Ok(versions.iter().find(|(parsed, _)| *parsed < current).map(|(_, num)| num.clone()))
+}
 
-    // Check out the that version's tag and build the rustdoc JSON.
-    cmd!("git", "checkout", tag_name)
-        .dir(&local_repo_path)
-        .run()?;
+/// Resolve the newest published version of `crate_name` that isn't a pre-release or yanked.
+///
+/// Convenience wrapper around `stable_versions` + `max_stable_version` for callers that only need
+/// the current version, not the full list.
+fn resolve_max_stable_version(
+    client: &SyncClient,
+    crate_name: &str,
+    fallback_version: &str,
+) -> anyhow::Result<String> {
+    Ok(max_stable_version(&stable_versions(client, crate_name)?, fallback_version))
+}
 
-    // Usually, explicitly specifying the package to build works fine, so we try that first.
+/// Run `cargo +nightly rustdoc` inside `work_dir`, producing JSON docs for `crate_name`.
+///
+/// Usually explicitly specifying the package to build works fine, so we try that first, then
+/// fall back to a handful of variants based on what cargo complains about: an ambiguous package
+/// name (e.g. `syn` at `github.com/dtolnay/syn` contains more than one package by that name), a
+/// workspace member that's a `--lib`-only target, and crates that don't build with
+/// `--all-features` because some of their features conflict with each other.
+///
+/// `version` is passed through via rustdoc's `--crate-version` so the emitted JSON is
+/// self-describing and consumers don't have to parse it back out of our filename convention.
+fn build_rustdoc_json(work_dir: &str, crate_name: &str, version: &str) -> anyhow::Result<()> {
     let explicit_pkg_build = cmd!(
         "cargo",
         "+nightly",
         "rustdoc",
         "--package",
-        &crate_info.name,
+        crate_name,
         "--all-features",
         "--",
         "--document-private-items",
         "-Zunstable-options",
         "--output-format",
-        "json"
+        "json",
+        "--crate-version",
+        version
     )
-    .dir(&local_repo_path)
+    .dir(work_dir)
     .stderr_capture()
     .unchecked()
     .run()
@@ -122,9 +136,6 @@ fn process_github_repo(
     if !explicit_pkg_build.status.success() {
         let stderr = String::from_utf8_lossy(&explicit_pkg_build.stderr);
         if stderr.contains("ambiguous") {
-            // Some crates (e.g. `syn` at `github.com/dtolnay/syn`) contain an ambiguous definition
-            // of the package by the name we're looking for.
-            //
             // In this case, we try to let rustdoc pick a correct option by default, which might work.
             let implicit_pkg_build = cmd!(
                 "cargo",
@@ -135,9 +146,11 @@ fn process_github_repo(
                 "--document-private-items",
                 "-Zunstable-options",
                 "--output-format",
-                "json"
+                "json",
+                "--crate-version",
+                version
             )
-            .dir(&local_repo_path)
+            .dir(work_dir)
             .run();
 
             if implicit_pkg_build.is_err() {
@@ -152,9 +165,11 @@ fn process_github_repo(
                     "--document-private-items",
                     "-Zunstable-options",
                     "--output-format",
-                    "json"
+                    "json",
+                    "--crate-version",
+                    version
                 )
-                .dir(&local_repo_path)
+                .dir(work_dir)
                 .run()?;
             }
         } else if stderr.contains("--lib") && stderr.contains("single target") {
@@ -163,16 +178,18 @@ fn process_github_repo(
                 "+nightly",
                 "rustdoc",
                 "--package",
-                &crate_info.name,
+                crate_name,
                 "--lib",
                 "--all-features",
                 "--",
                 "--document-private-items",
                 "-Zunstable-options",
                 "--output-format",
-                "json"
+                "json",
+                "--crate-version",
+                version
             )
-            .dir(&local_repo_path)
+            .dir(work_dir)
             .run();
 
             if lib_pkg_build.is_err() {
@@ -184,15 +201,17 @@ fn process_github_repo(
                     "+nightly",
                     "rustdoc",
                     "--package",
-                    &crate_info.name,
+                    crate_name,
                     "--lib",
                     "--",
                     "--document-private-items",
                     "-Zunstable-options",
                     "--output-format",
-                    "json"
+                    "json",
+                    "--crate-version",
+                    version
                 )
-                .dir(&local_repo_path)
+                .dir(work_dir)
                 .run()?;
             }
         } else {
@@ -204,91 +223,469 @@ fn process_github_repo(
                 "+nightly",
                 "rustdoc",
                 "--package",
-                &crate_info.name,
+                crate_name,
                 "--",
                 "--document-private-items",
                 "-Zunstable-options",
                 "--output-format",
-                "json"
+                "json",
+                "--crate-version",
+                version
             )
-            .dir(&local_repo_path)
+            .dir(work_dir)
             .run()?;
         }
     }
 
-    // Rustdoc JSON is output under a filename that replaces underscores with dashes.
-    let underscored_crate_name = crate_info.name.as_str().replace('-', "_");
+    Ok(())
+}
+
+/// Where `emit_rustdoc_json` places the JSON for `crate_name` at `version`.
+///
+/// Rustdoc JSON is output under a filename that replaces dashes with underscores.
+fn rustdoc_json_path(crate_name: &str, version: &str) -> String {
+    let underscored_crate_name = crate_name.replace('-', "_");
+    format!("./localdata/rustdocs/{}-{}.json", underscored_crate_name, version)
+}
+
+/// Move the rustdoc JSON that `build_rustdoc_json` produced in `work_dir` into `./localdata/rustdocs/`.
+fn emit_rustdoc_json(work_dir: &str, crate_name: &str, version: &str) -> anyhow::Result<()> {
+    let underscored_crate_name = crate_name.replace('-', "_");
     cmd!(
         "mv",
-        format!(
-            "{}/{}/{}.json",
-            local_repo_path, "target/doc", underscored_crate_name
-        ),
-        format!(
-            "./localdata/rustdocs/{}-{}.json",
-            underscored_crate_name, max_version
-        ),
+        format!("{}/{}/{}.json", work_dir, "target/doc", underscored_crate_name),
+        rustdoc_json_path(crate_name, version),
     )
     .run()?;
 
     Ok(())
 }
 
-fn attempt_cargo_semver_checks(local_repo_path: &str, package_name: &str) -> anyhow::Result<()> {
-    let cargo_semver_checks_bin = "/home/predrag/.scratch/.rustc-target/cargo-semver-check/target/release/cargo-semver-checks";
-    cmd!(
-        cargo_semver_checks_bin,
-        "semver-checks",
-        "check-release",
-        "--package",
-        package_name,
-    )
-    .dir(local_repo_path)
-    .run()?;
+/// Download the published `.crate` tarball for `crate_name` at `version` and unpack it.
+///
+/// This guarantees we document precisely what was published to crates.io: no tag-naming
+/// guesswork, and it works even for crates whose repos don't tag releases at all. The tarball
+/// always contains a single top-level `{crate_name}-{version}/` directory, which we strip so the
+/// crate's own files end up directly under the returned directory.
+///
+/// Extraction goes through `Entry::unpack_in`, not the bare `Entry::unpack` used by `tar`'s
+/// lower-level API: the tarball comes from crates.io, a registry anyone can publish to, so its
+/// entries are untrusted, and `unpack_in` is what actually guards against a malicious publish —
+/// it rejects entry paths that would escape the destination *and*, unlike a hand-rolled path
+/// check, refuses to follow a symlink entry whose `link_name` points outside of it, which a bare
+/// `unpack()` call happily creates as-is.
+///
+/// Extraction happens in a sibling `.tmp` directory that's renamed into place only once every
+/// entry has unpacked successfully, so a run killed mid-extraction (or mid-build, since the same
+/// directory is reused for the build) can never leave behind a `dest_dir` that looks "already
+/// extracted" but is actually corrupt or partial.
+fn fetch_and_extract_crate_tarball(crate_name: &str, version: &str) -> anyhow::Result<String> {
+    let dest_dir = format!("{}{}-{}", BASE_PATH, crate_name, version);
+    if Path::new(&dest_dir).is_dir() {
+        println!(">> already extracted {dest_dir}");
+        return Ok(dest_dir);
+    }
 
-    Ok(())
+    let url = format!("{}{}/{}-{}.crate", CRATES_IO_STATIC_PREFIX, crate_name, crate_name, version);
+    println!(">> downloading {url}");
+    let tarball = reqwest::blocking::get(&url)?.error_for_status()?.bytes()?;
+
+    let tmp_dir = format!("{dest_dir}.tmp");
+    if Path::new(&tmp_dir).is_dir() {
+        std::fs::remove_dir_all(&tmp_dir)?;
+    }
+    std::fs::create_dir_all(&tmp_dir)?;
+
+    let decoder = GzDecoder::new(tarball.as_ref());
+    let mut archive = Archive::new(decoder);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.unpack_in(&tmp_dir)? {
+            anyhow::bail!(
+                "refusing to extract unsafe tar entry path: {}",
+                entry.path()?.display()
+            );
+        }
+    }
+
+    let unpacked_dir = format!("{}/{}-{}", tmp_dir, crate_name, version);
+    std::fs::rename(&unpacked_dir, &dest_dir)?;
+    std::fs::remove_dir_all(&tmp_dir)?;
+    Ok(dest_dir)
 }
 
-fn main() -> anyhow::Result<()> {
-    let check_semver = false;
+/// Acquire the crate's published source via its `.crate` tarball and build rustdoc JSON from it.
+///
+/// Returns the directory the tarball was extracted into, for [`BuildCache`] bookkeeping.
+fn process_crate_tarball(crate_name: &str, version: &str) -> anyhow::Result<String> {
+    println!("* crate {} {} (tarball) *", crate_name, version);
 
-    let client = crates_io_api::SyncClient::new(
-        "crates-rustdoc-gen (obi1kenobi82@gmail.com)",
-        Duration::from_secs(1),
-    )?;
+    let extracted_path = fetch_and_extract_crate_tarball(crate_name, version)?;
+    build_rustdoc_json(&extracted_path, crate_name, version)?;
+    emit_rustdoc_json(&extracted_path, crate_name, version)?;
+    Ok(extracted_path)
+}
 
-    let mut query = CratesQuery::builder()
-        .page_size(100)
-        .sort(Sort::Downloads)
-        .build();
-    query.set_page(1);
+/// Acquire the crate's source by cloning its GitHub repo and checking out the matching tag.
+///
+/// This is the fallback path for crates whose published tarball fails to build, since the git
+/// tag dance is inherently less reliable than building exactly what was published: tag naming is
+/// inconsistent across repos, and some repos don't tag releases at all.
+///
+/// Returns `None` (rather than an error) if no matching version tag could be found, since that's
+/// not a build failure worth caching, just nothing to do. On success, returns the local clone's
+/// directory together with the tag that was checked out, for [`BuildCache`] bookkeeping.
+fn process_github_repo(
+    crate_info: &Crate,
+    repo_path: &str,
+    version: &str,
+) -> anyhow::Result<Option<(String, String)>> {
+    println!("* crate {} {} (git clone) *", &crate_info.name, version);
 
-    let mut failures = vec![];
+    let org_and_repo_name = repo_path
+        .strip_prefix(GITHUB_PREFIX)
+        .expect("invalid prefix")
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+    let git_path = format!("{}{}.git", GITHUB_GIT_PREFIX, org_and_repo_name);
 
-    for crate_info in client.crates(query)?.crates.into_iter() {
-        if let Some(repository) = crate_info.repository.as_deref() {
-            if repository.starts_with(GITHUB_PREFIX) {
-                if let Err(e) = process_github_repo(&crate_info, repository, check_semver) {
-                    failures.push((crate_info, e));
-                }
-            } else {
+    let repo_name = org_and_repo_name.replace('/', "__");
+    let local_repo_path = format!("{}{}", BASE_PATH, repo_name);
+
+    // Have we already downloaded this repo?
+    if Path::new(&format!("{}/.git", &local_repo_path)).is_dir() {
+        // Yes! Run "git fetch" to make sure it's up to date.
+        println!(">> refreshing repo {local_repo_path}");
+        cmd!("git", "fetch").dir(&local_repo_path).run()?;
+    } else {
+        // No. Clone it into that directory.
+        println!(">> cloning into {local_repo_path}");
+        cmd!("git", "clone", git_path, &local_repo_path).run()?;
+    }
+
+    let tag_name = match discover_tag_format(&local_repo_path, &crate_info.name, version) {
+        Some(tag_name) => tag_name,
+        None => {
+            println!(
+                ">> could not find version tag for crate version {} {} in repo {}",
+                crate_info.name, version, local_repo_path
+            );
+            return Ok(None);
+        }
+    };
+
+    // Check out the that version's tag and build the rustdoc JSON.
+    cmd!("git", "checkout", &tag_name)
+        .dir(&local_repo_path)
+        .run()?;
+
+    build_rustdoc_json(&local_repo_path, &crate_info.name, version)?;
+    emit_rustdoc_json(&local_repo_path, &crate_info.name, version)?;
+    Ok(Some((local_repo_path, tag_name)))
+}
+
+/// Ensure rustdoc JSON for `crate_info` at `version` exists, preferring the published tarball
+/// over a git clone since it guarantees we document exactly what was published. Falls back to
+/// cloning the GitHub repo and checking out the matching tag if the tarball fails to build.
+///
+/// Consults `cache` before doing any work, and skips crates whose `(name, version)` already has
+/// a successful entry pointing at an existing file. `cache` is behind a `Mutex` rather than taken
+/// as `&mut` so that many calls can run concurrently across worker threads, each only briefly
+/// holding the lock to check or update the manifest, never while cloning or building.
+///
+/// Saves `cache` to `CACHE_MANIFEST_PATH` right after recording each outcome, rather than once at
+/// the end of the whole run: a multi-hour `--top 5000` run is exactly the case the persistent
+/// cache exists for, and a build killed or crashed partway through should only have to redo the
+/// crates it hadn't finished yet, not every crate in the batch.
+///
+/// Returns the path to the JSON, or `None` if no matching git tag could be found for a crate
+/// whose tarball also failed to build.
+fn ensure_rustdoc_json(
+    crate_info: &Crate,
+    repo_path: &str,
+    version: &str,
+    cache: &Mutex<BuildCache>,
+) -> anyhow::Result<Option<String>> {
+    let output_path = rustdoc_json_path(&crate_info.name, version);
+
+    if cache.lock().unwrap().has_fresh_success(&crate_info.name, version) {
+        println!(">> skipping crate {} {version}, already built", &crate_info.name);
+        return Ok(Some(output_path));
+    }
+
+    let outcome = match process_crate_tarball(&crate_info.name, version) {
+        Ok(work_dir) => Ok(Some((work_dir, "tarball".to_owned()))),
+        Err(e) => {
+            println!(
+                ">> tarball build failed for crate {} {version}: {e}, falling back to git clone",
+                &crate_info.name,
+            );
+            process_github_repo(crate_info, repo_path, version)
+        }
+    };
+
+    match outcome {
+        Ok(Some((work_dir, source))) => {
+            let mut cache = cache.lock().unwrap();
+            cache.record(
+                &crate_info.name,
+                version,
+                BuildStatus::Success,
+                &output_path,
+                &work_dir,
+                &source,
+            );
+            cache.save(CACHE_MANIFEST_PATH)?;
+            Ok(Some(output_path))
+        }
+        Ok(None) => Ok(None),
+        Err(e) => {
+            let mut cache = cache.lock().unwrap();
+            cache.record(
+                &crate_info.name,
+                version,
+                BuildStatus::Failure,
+                &output_path,
+                "",
+                "",
+            );
+            cache.save(CACHE_MANIFEST_PATH)?;
+            Err(e)
+        }
+    }
+}
+
+/// Resolve the crate's max stable version and ensure rustdoc JSON exists for it.
+///
+/// Returns the resolved version alongside the result, rather than just `crate_info.max_version`,
+/// so callers can log the version that was actually attempted even on failure -- which may well
+/// differ from `max_version` if it pointed at a pre-release.
+fn process_crate(
+    client: &SyncClient,
+    crate_info: &Crate,
+    repo_path: &str,
+    cache: &Mutex<BuildCache>,
+) -> (String, anyhow::Result<()>) {
+    let max_version = match resolve_max_stable_version(client, &crate_info.name, &crate_info.max_version) {
+        Ok(max_version) => max_version,
+        Err(e) => return (crate_info.max_version.clone(), Err(e)),
+    };
+    println!(
+        "* crate {} max stable version is {max_version}",
+        &crate_info.name
+    );
+
+    let result = ensure_rustdoc_json(crate_info, repo_path, &max_version, cache).map(|_| ());
+    (max_version, result)
+}
+
+/// Build rustdoc JSON for both the crate's max stable version and the stable version immediately
+/// preceding it, then run `cargo-semver-checks` across the pair and print a one-line summary.
+///
+/// This doubles the run as a corpus for validating semver-compat tooling across the ecosystem's
+/// most-downloaded crates, since every pair is a real published baseline/current release.
+///
+/// Returns the resolved current version alongside the result, rather than just
+/// `crate_info.max_version`, so callers can log the version that was actually attempted even on
+/// failure -- which may well differ from `max_version` if it pointed at a pre-release.
+fn process_crate_semver_diff(
+    client: &SyncClient,
+    crate_info: &Crate,
+    repo_path: &str,
+    cache: &Mutex<BuildCache>,
+) -> (String, anyhow::Result<()>) {
+    // Fetch the version list once and derive both the current and preceding stable versions from
+    // it, rather than two separate `stable_versions` calls, since each one is a full metadata
+    // round-trip through the single rate-limited client.
+    let versions = match stable_versions(client, &crate_info.name) {
+        Ok(versions) => versions,
+        Err(e) => return (crate_info.max_version.clone(), Err(e)),
+    };
+    let new_version = max_stable_version(&versions, &crate_info.max_version);
+
+    let result = (|| -> anyhow::Result<()> {
+        let old_version = match preceding_stable_version(&versions, &new_version)? {
+            Some(old_version) => old_version,
+            None => {
                 println!(
-                    "Crate {} has a non-GitHub repository {}",
-                    &crate_info.name, &repository
+                    ">> crate {} has no stable version preceding {new_version}, skipping semver diff",
+                    &crate_info.name
                 );
+                return Ok(());
             }
-        } else {
-            println!("Crate {} doesn't have repo information", &crate_info.name);
+        };
+
+        let new_json = ensure_rustdoc_json(crate_info, repo_path, &new_version, cache)?;
+        let old_json = ensure_rustdoc_json(crate_info, repo_path, &old_version, cache)?;
+
+        let (Some(old_json), Some(new_json)) = (old_json, new_json) else {
+            println!(
+                ">> crate {}: could not build both {old_version} and {new_version}, skipping semver diff",
+                &crate_info.name
+            );
+            return Ok(());
+        };
+
+        let has_breaking_changes = semver_check::diff_rustdoc_json(&old_json, &new_json)?;
+        let bump = semver_check::classify_bump(
+            &semver::Version::parse(&old_version)?,
+            &semver::Version::parse(&new_version)?,
+        );
+
+        println!(
+            "{}: {old_version} -> {new_version} is a {bump} bump; cargo-semver-checks {}",
+            &crate_info.name,
+            if has_breaking_changes {
+                "flagged breaking changes"
+            } else {
+                "found no breaking changes"
+            },
+        );
+
+        Ok(())
+    })();
+
+    (new_version, result)
+}
+
+static DEFAULT_PRUNE_MAX_AGE_DAYS: u64 = 30;
+
+/// Delete cached repos/tarballs and JSON not touched within `max_age_days`, reporting reclaimed bytes.
+fn run_prune(max_age_days: u64) -> anyhow::Result<()> {
+    let mut cache = BuildCache::load(CACHE_MANIFEST_PATH)?;
+    let reclaimed_bytes = cache.prune(max_age_days * 24 * 60 * 60)?;
+    cache.save(CACHE_MANIFEST_PATH)?;
+    println!(
+        "pruned entries not touched in the last {max_age_days} days, reclaimed {reclaimed_bytes} bytes"
+    );
+    Ok(())
+}
+
+static DEFAULT_TOP: usize = 100;
+static DEFAULT_CONCURRENCY: usize = 4;
+
+/// Page through `client.crates()` (100 crates per page, respecting the client's own rate limit)
+/// until `top_n` download-ranked crates have been collected.
+fn collect_top_crates(client: &SyncClient, top_n: usize) -> anyhow::Result<Vec<Crate>> {
+    let mut collected = Vec::with_capacity(top_n);
+    let mut page = 1u64;
+
+    while collected.len() < top_n {
+        let mut query = CratesQuery::builder()
+            .page_size(100)
+            .sort(Sort::Downloads)
+            .build();
+        query.set_page(page);
+
+        let response = client.crates(query)?;
+        if response.crates.is_empty() {
+            break;
         }
+
+        collected.extend(response.crates);
+        page += 1;
     }
 
+    collected.truncate(top_n);
+    Ok(collected)
+}
+
+/// Process `crates` across a bounded pool of `concurrency` worker threads.
+///
+/// Each crate's clone+build is independent and I/O/CPU-bound, so we parallelize that part, while
+/// `client` (metadata calls) and `cache` stay behind shared references that worker threads
+/// briefly lock rather than exclusively own. Failures are collected into a single `Vec` for
+/// `errors.log`.
+///
+/// `concurrency` is clamped to at least 1: a `0` there would silently spawn no worker threads and
+/// process nothing, with no indication that the run was a no-op.
+fn process_crates_concurrently(
+    client: &SyncClient,
+    crates: Vec<Crate>,
+    check_semver: bool,
+    cache: &Mutex<BuildCache>,
+    concurrency: usize,
+) -> Vec<(Crate, String, anyhow::Error)> {
+    let concurrency = concurrency.max(1);
+    let work_queue = Mutex::new(VecDeque::from(crates));
+    let failures = Mutex::new(Vec::new());
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency {
+            scope.spawn(|| loop {
+                let crate_info = match work_queue.lock().unwrap().pop_front() {
+                    Some(crate_info) => crate_info,
+                    None => break,
+                };
+
+                if let Some(repository) = crate_info.repository.clone() {
+                    if repository.starts_with(GITHUB_PREFIX) {
+                        let (attempted_version, result) = if check_semver {
+                            process_crate_semver_diff(client, &crate_info, &repository, cache)
+                        } else {
+                            process_crate(client, &crate_info, &repository, cache)
+                        };
+                        if let Err(e) = result {
+                            failures.lock().unwrap().push((crate_info, attempted_version, e));
+                        }
+                    } else {
+                        println!(
+                            "Crate {} has a non-GitHub repository {}",
+                            &crate_info.name, &repository
+                        );
+                    }
+                } else {
+                    println!("Crate {} doesn't have repo information", &crate_info.name);
+                }
+            });
+        }
+    });
+
+    failures.into_inner().unwrap()
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("prune") {
+        args.next();
+        let max_age_days = args
+            .next()
+            .and_then(|age| age.parse().ok())
+            .unwrap_or(DEFAULT_PRUNE_MAX_AGE_DAYS);
+        return run_prune(max_age_days);
+    }
+
+    let mut top_n = DEFAULT_TOP;
+    let mut concurrency = DEFAULT_CONCURRENCY;
+    let mut check_semver = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => top_n = args.next().and_then(|v| v.parse().ok()).unwrap_or(top_n),
+            "--concurrency" => {
+                concurrency = args.next().and_then(|v| v.parse().ok()).unwrap_or(concurrency)
+            }
+            "--check-semver" => check_semver = true,
+            other => println!("ignoring unrecognized argument: {other}"),
+        }
+    }
+
+    let client = crates_io_api::SyncClient::new(
+        "crates-rustdoc-gen (obi1kenobi82@gmail.com)",
+        Duration::from_secs(1),
+    )?;
+
+    let crates = collect_top_crates(&client, top_n)?;
+    let cache = Mutex::new(BuildCache::load(CACHE_MANIFEST_PATH)?);
+
+    let failures = process_crates_concurrently(&client, crates, check_semver, &cache, concurrency);
+
     let error_log_file = File::create("./localdata/errors.log")?;
     let mut writer = BufWriter::new(error_log_file);
-    for (crate_info, error_info) in failures.into_iter() {
+    for (crate_info, attempted_version, error_info) in failures.into_iter() {
         writeln!(
             &mut writer,
             "*** {} - {} failed:\n{}",
-            crate_info.name, crate_info.max_version, error_info
+            crate_info.name, attempted_version, error_info
         )?;
     }
 
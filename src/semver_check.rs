@@ -0,0 +1,57 @@
+use std::fmt;
+
+use duct::cmd;
+
+/// The kind of version bump between two releases, inferred by comparing their version numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BumpKind {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl fmt::Display for BumpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BumpKind::Major => "major",
+            BumpKind::Minor => "minor",
+            BumpKind::Patch => "patch",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Classify the bump from `old` to `new` using Cargo's own `^`-compatibility rule, not raw SemVer
+/// major/minor/patch comparison: below `1.0.0`, Cargo treats a minor bump as breaking the same way
+/// it treats a major bump at `>=1.0.0`, and below `0.1.0` even a patch bump is breaking. Matching
+/// that convention, rather than the SemVer spec's, is the whole point of this corpus -- it's meant
+/// to validate semver-compat tooling against how the ecosystem it actually targets behaves.
+pub fn classify_bump(old: &semver::Version, new: &semver::Version) -> BumpKind {
+    let caret_req = semver::VersionReq::parse(&format!("{}.{}.{}", old.major, old.minor, old.patch))
+        .expect("a fully-specified version is always a valid caret requirement");
+    if !caret_req.matches(new) {
+        BumpKind::Major
+    } else if old.minor != new.minor {
+        BumpKind::Minor
+    } else {
+        BumpKind::Patch
+    }
+}
+
+/// Run `cargo-semver-checks` (discovered on `PATH`, not a hardcoded path) against a pair of
+/// already-generated rustdoc JSON files, returning whether it flagged any breaking changes.
+pub fn diff_rustdoc_json(baseline_json: &str, current_json: &str) -> anyhow::Result<bool> {
+    let result = cmd!(
+        "cargo-semver-checks",
+        "semver-checks",
+        "check-release",
+        "--baseline-rustdoc",
+        baseline_json,
+        "--current-rustdoc",
+        current_json,
+    )
+    .unchecked()
+    .run()?;
+
+    Ok(!result.status.success())
+}